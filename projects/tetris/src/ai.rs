@@ -0,0 +1,175 @@
+//! Heuristic auto-player.
+//!
+//! On each new piece, enumerates every landing candidate (4 rotations x every
+//! horizontal offset), scores the resulting board with a weighted linear
+//! heuristic in the style of a one-piece Tetris evaluator, and picks the best
+//! one. Each candidate is scored on a throwaway cloned board, so there's no
+//! explicit undo.
+
+use crate::{shape_fits, Board, GameState, BASE_SHAPES, BOARD_HEIGHT, BOARD_WIDTH};
+
+// Heuristic weights: score = LINES*lines - HEIGHT*aggregate_height - HOLES*holes - BUMPINESS*bumpiness
+const WEIGHT_LINES: f64 = 0.76;
+const WEIGHT_HEIGHT: f64 = 0.51;
+const WEIGHT_HOLES: f64 = 0.36;
+const WEIGHT_BUMPINESS: f64 = 0.18;
+
+// Where the best placement found for the current piece ended up.
+pub struct Placement {
+    pub rotation: usize,
+    pub x: isize,
+}
+
+// Search every rotation/offset for `shape_type` against `board` and return
+// the placement with the highest heuristic score.
+pub fn best_placement(board: &Board, shape_type: usize) -> Option<Placement> {
+    let mut best: Option<(Placement, f64)> = None;
+
+    for rotation in 0..4 {
+        let shape = rotated_shape(shape_type, rotation);
+        for x in -3..=(BOARD_WIDTH as isize) {
+            if !shape_fits(board, &shape, x, 0) {
+                continue;
+            }
+
+            let mut y = 0;
+            while shape_fits(board, &shape, x, y + 1) {
+                y += 1;
+            }
+
+            let mut landed = *board;
+            place_shape(&mut landed, &shape, x, y, shape_type);
+            let lines = clear_full_rows(&mut landed);
+            let score = evaluate_board(&landed, lines);
+
+            if best.as_ref().map_or(true, |(_, best_score)| score > *best_score) {
+                best = Some((Placement { rotation, x }, score));
+            }
+        }
+    }
+
+    best.map(|(placement, _)| placement)
+}
+
+// Step the real current piece one move closer to `target`, then hard-drop
+// once it's lined up. Mirrors the same rotation/move/drop the keyboard uses.
+pub fn advance_toward(game: &mut GameState, target: &Placement) {
+    if game.current_piece.rotation != target.rotation {
+        crate::handle_rotation(game, 1);
+        return;
+    }
+
+    if game.current_piece.x != target.x {
+        let mut test_piece = game.current_piece;
+        let step = if target.x > game.current_piece.x { 1 } else { -1 };
+        test_piece.x += step;
+        if game.is_valid_position(&test_piece) {
+            game.current_piece.x += step;
+            game.reset_lock_delay();
+        }
+        return;
+    }
+
+    let start_y = game.current_piece.y;
+    while game.is_valid_position(&game.current_piece) {
+        game.current_piece.y += 1;
+    }
+    game.current_piece.y -= 1;
+    game.score += 2 * (game.current_piece.y - start_y) as u32;
+    game.lock_piece();
+}
+
+// Rotate `BASE_SHAPES[shape_type]` clockwise `rotation` times, matching the
+// transform `handle_rotation` applies to the live piece.
+fn rotated_shape(shape_type: usize, rotation: usize) -> [[u8; 4]; 4] {
+    let mut shape = BASE_SHAPES[shape_type];
+    for _ in 0..rotation {
+        let mut rotated = [[0u8; 4]; 4];
+        for y in 0..4 {
+            for x in 0..4 {
+                if shape[y][x] != 0 {
+                    rotated[x][3 - y] = 1;
+                }
+            }
+        }
+        shape = rotated;
+    }
+    shape
+}
+
+fn place_shape(board: &mut Board, shape: &[[u8; 4]; 4], x: isize, y: isize, shape_type: usize) {
+    for sy in 0..4 {
+        for sx in 0..4 {
+            if shape[sy][sx] != 0 {
+                let board_x = (x + sx as isize) as usize;
+                let board_y = y + sy as isize;
+                if board_y >= 0 {
+                    board[board_y as usize][board_x] = shape_type as u8 + 1;
+                }
+            }
+        }
+    }
+}
+
+// Collapses full rows like `GameState::finish_line_clear`, but just reports
+// the count instead of touching score/level. Row 0 is not checked.
+fn clear_full_rows(board: &mut Board) -> u32 {
+    let mut lines_cleared = 0;
+    let mut y = BOARD_HEIGHT - 1;
+
+    while y > 0 {
+        if board[y].iter().all(|&cell| cell != 0) {
+            lines_cleared += 1;
+            for row in (1..=y).rev() {
+                board[row] = board[row - 1];
+            }
+            board[0] = [0; BOARD_WIDTH];
+        } else {
+            y -= 1;
+        }
+    }
+
+    lines_cleared
+}
+
+fn column_heights(board: &Board) -> [u32; BOARD_WIDTH] {
+    let mut heights = [0u32; BOARD_WIDTH];
+    for x in 0..BOARD_WIDTH {
+        for y in 0..BOARD_HEIGHT {
+            if board[y][x] != 0 {
+                heights[x] = (BOARD_HEIGHT - y) as u32;
+                break;
+            }
+        }
+    }
+    heights
+}
+
+// A hole is an empty cell with a filled cell somewhere above it in the same column.
+fn count_holes(board: &Board, heights: &[u32; BOARD_WIDTH]) -> u32 {
+    let mut holes = 0;
+    for x in 0..BOARD_WIDTH {
+        let top_y = BOARD_HEIGHT - heights[x] as usize;
+        for y in top_y..BOARD_HEIGHT {
+            if board[y][x] == 0 {
+                holes += 1;
+            }
+        }
+    }
+    holes
+}
+
+fn evaluate_board(board: &Board, lines_cleared: u32) -> f64 {
+    let heights = column_heights(board);
+    let aggregate_height: u32 = heights.iter().sum();
+    let holes = count_holes(board, &heights);
+    let bumpiness: u32 = heights
+        .windows(2)
+        .map(|pair| (pair[0] as i32 - pair[1] as i32).unsigned_abs())
+        .sum();
+
+    WEIGHT_LINES * lines_cleared as f64
+        - WEIGHT_HEIGHT * aggregate_height as f64
+        - WEIGHT_HOLES * holes as f64
+        - WEIGHT_BUMPINESS * bumpiness as f64
+}
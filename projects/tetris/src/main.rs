@@ -1,5 +1,13 @@
 use macroquad::prelude::*;
 use macroquad::color::Color;
+use std::collections::VecDeque;
+
+// Optional MIDI pad-grid controller backend (input + LED mirroring).
+#[cfg(feature = "midi")]
+mod midi;
+
+// Heuristic auto-player, toggled on with a key at runtime.
+mod ai;
 
 // --- Color Constants ---
 // We define colors manually to avoid issues with library imports.
@@ -13,6 +21,9 @@ const BOARD_WIDTH: usize = 10;
 const BOARD_HEIGHT: usize = 20;
 const CELL_SIZE: f32 = 36.0;
 
+// The board grid, shared by `GameState` and the AI's board simulations.
+type Board = [[u8; BOARD_WIDTH]; BOARD_HEIGHT];
+
 // Screen dimensions derived from board constants
 const SCREEN_WIDTH: f32 = (BOARD_WIDTH as f32) * CELL_SIZE + 200.0; // Add space for UI
 const SCREEN_HEIGHT: f32 = (BOARD_HEIGHT as f32) * CELL_SIZE;
@@ -22,7 +33,27 @@ const BOARD_X_OFFSET: f32 = (SCREEN_WIDTH - (BOARD_WIDTH as f32 * CELL_SIZE)) /
 const BOARD_Y_OFFSET: f32 = 0.0;
 
 // Game timing (in seconds)
-const FALL_DELAY: f64 = 0.5;
+// Gravity speeds up with level: seconds-per-row shrinks from 0.5 down toward
+// ~0.05 over ~20 levels, then holds at the floor.
+const LINES_PER_LEVEL: u32 = 10;
+const MAX_GRAVITY_LEVEL: u32 = 20;
+const MIN_FALL_DELAY: f64 = 0.05;
+const BASE_FALL_DELAY: f64 = 0.5;
+
+fn fall_delay_for_level(level: u32) -> f64 {
+    let step = level.min(MAX_GRAVITY_LEVEL) as f64 - 1.0;
+    let delay = BASE_FALL_DELAY - step * (BASE_FALL_DELAY - MIN_FALL_DELAY) / (MAX_GRAVITY_LEVEL - 1) as f64;
+    delay.max(MIN_FALL_DELAY)
+}
+
+// Lock delay: once a piece is grounded it gets this long before it hard-locks,
+// and a successful move/rotation while grounded can push the deadline back out
+// (up to MAX_LOCK_RESETS times, matching bounded "infinity" lock delay).
+const LOCK_DELAY: f64 = 0.5;
+const MAX_LOCK_RESETS: u32 = 15;
+
+// How long full rows flash before they actually collapse.
+const FLASH_DURATION: f64 = 0.2;
 
 // --- SRS and Piece Data ---
 // Base shapes for each tetromino at rotation 0
@@ -65,6 +96,28 @@ const TETROMINO_COLORS: [Color; 8] = [
     Color { r: 1.0, g: 0.65, b: 0.0, a: 1.0 },  // 7: Orange
 ];
 
+// How many upcoming pieces are shown in the next-piece preview.
+const NEXT_QUEUE_SIZE: usize = 3;
+
+// --- 7-Bag Randomizer ---
+// Refill the bag with a shuffled permutation of all 7 tetrominoes.
+fn refill_bag(bag: &mut Vec<usize>) {
+    let mut pieces: Vec<usize> = (0..7).collect();
+    for i in (1..pieces.len()).rev() {
+        let j = rand::gen_range(0, i + 1);
+        pieces.swap(i, j);
+    }
+    bag.extend(pieces);
+}
+
+// Pull the next piece type from the bag, refilling it first if empty.
+fn next_from_bag(bag: &mut Vec<usize>) -> usize {
+    if bag.is_empty() {
+        refill_bag(bag);
+    }
+    bag.remove(0)
+}
+
 // --- Structs ---
 
 #[derive(Clone, Copy)]
@@ -89,21 +142,50 @@ impl Piece {
 }
 
 struct GameState {
-    board: [[u8; BOARD_WIDTH]; BOARD_HEIGHT],
+    board: Board,
     current_piece: Piece,
     last_fall_time: f64,
     score: u32,
     game_over: bool,
+    level: u32,
+    lines_cleared: u32,
+    bag: Vec<usize>,
+    next_queue: VecDeque<usize>,
+    hold: Option<usize>,
+    hold_used: bool,
+    // Some(deadline) while the piece is grounded and waiting to lock.
+    lock_deadline: Option<f64>,
+    lock_resets: u32,
+    // Non-empty while full rows are flashing, waiting to collapse.
+    flash_rows: Vec<usize>,
+    flash_deadline: Option<f64>,
 }
 
 impl GameState {
     fn new() -> Self {
+        let mut bag = Vec::new();
+        let mut next_queue = VecDeque::new();
+        for _ in 0..NEXT_QUEUE_SIZE {
+            next_queue.push_back(next_from_bag(&mut bag));
+        }
+        let current_piece = Piece::new(next_from_bag(&mut bag));
+
         GameState {
             board: [[0; BOARD_WIDTH]; BOARD_HEIGHT],
-            current_piece: Piece::new(rand::gen_range(0, 7)),
+            current_piece,
             last_fall_time: get_time(),
             score: 0,
             game_over: false,
+            level: 1,
+            lines_cleared: 0,
+            bag,
+            next_queue,
+            hold: None,
+            hold_used: false,
+            lock_deadline: None,
+            lock_resets: 0,
+            flash_rows: Vec::new(),
+            flash_deadline: None,
         }
     }
 
@@ -111,30 +193,30 @@ impl GameState {
         *self = GameState::new();
     }
 
-    // Check if the piece's current position is valid
-    fn is_valid_position(&self, piece: &Piece) -> bool {
-        for y in 0..4 {
-            for x in 0..4 {
-                if piece.shape[y][x] != 0 {
-                    let board_x = piece.x + x as isize;
-                    let board_y = piece.y + y as isize;
+    // Is the current piece resting on something (one row down is blocked)?
+    fn is_grounded(&self) -> bool {
+        let mut test_piece = self.current_piece;
+        test_piece.y += 1;
+        !self.is_valid_position(&test_piece)
+    }
 
-                    // Check bounds
-                    if board_x < 0 || board_x >= BOARD_WIDTH as isize || board_y >= BOARD_HEIGHT as isize {
-                        return false;
-                    }
-                    
-                    // Check collision with existing blocks (only if on board)
-                    if board_y >= 0 && self.board[board_y as usize][board_x as usize] != 0 {
-                        return false;
-                    }
-                }
-            }
+    // Push the lock-delay deadline back out after a successful move/rotation,
+    // as long as the piece is still grounded and hasn't used up its resets.
+    fn reset_lock_delay(&mut self) {
+        if self.lock_deadline.is_some() && self.is_grounded() && self.lock_resets < MAX_LOCK_RESETS {
+            self.lock_deadline = Some(get_time() + LOCK_DELAY);
+            self.lock_resets += 1;
         }
-        true
     }
-    
-    // Lock the current piece onto the board
+
+    // Check if the piece's current position is valid
+    fn is_valid_position(&self, piece: &Piece) -> bool {
+        shape_fits(&self.board, &piece.shape, piece.x, piece.y)
+    }
+
+    // Lock the current piece onto the board. If that completes any rows,
+    // flag them to flash instead of clearing immediately; otherwise spawn
+    // the next piece right away.
     fn lock_piece(&mut self) {
         for y in 0..4 {
             for x in 0..4 {
@@ -147,47 +229,117 @@ impl GameState {
                 }
             }
         }
-        self.clear_lines();
+
+        let full_rows: Vec<usize> = (0..BOARD_HEIGHT)
+            .filter(|&y| self.board[y].iter().all(|&cell| cell != 0))
+            .collect();
+
+        if full_rows.is_empty() {
+            self.spawn_new_piece();
+        } else {
+            self.flash_rows = full_rows;
+            self.flash_deadline = Some(get_time() + FLASH_DURATION);
+        }
+    }
+
+    // Are full rows currently flashing, waiting to collapse?
+    fn is_flashing(&self) -> bool {
+        !self.flash_rows.is_empty()
+    }
+
+    // Collapse the flashing rows, award score/level progress for them, and
+    // spawn the next piece. The counterpart to the flag-and-flash half of
+    // line clearing started in `lock_piece`.
+    fn finish_line_clear(&mut self) {
+        let lines_cleared = self.flash_rows.len() as u32;
+
+        let remaining: Vec<[u8; BOARD_WIDTH]> = (0..BOARD_HEIGHT)
+            .filter(|y| !self.flash_rows.contains(y))
+            .map(|y| self.board[y])
+            .collect();
+        let mut new_board = [[0; BOARD_WIDTH]; BOARD_HEIGHT];
+        let first_kept_row = BOARD_HEIGHT - remaining.len();
+        for (i, row) in remaining.into_iter().enumerate() {
+            new_board[first_kept_row + i] = row;
+        }
+        self.board = new_board;
+
+        let base_score = match lines_cleared {
+            1 => 100,
+            2 => 300,
+            3 => 500,
+            4 => 800,
+            _ => 0,
+        };
+        self.score += base_score * self.level;
+        self.lines_cleared += lines_cleared;
+        self.level = self.lines_cleared / LINES_PER_LEVEL + 1;
+
+        self.flash_rows.clear();
+        self.flash_deadline = None;
         self.spawn_new_piece();
     }
 
-    // Spawn a new piece, checking for game over
+    // Swap the current piece with the hold slot (or stash it if the slot is
+    // empty), limited to one use per drop via `hold_used`.
+    fn toggle_hold(&mut self) {
+        if self.hold_used {
+            return;
+        }
+        self.hold_used = true;
+        let current_type = self.current_piece.shape_type;
+        match self.hold {
+            Some(held_type) => {
+                self.current_piece = Piece::new(held_type);
+                self.hold = Some(current_type);
+            }
+            None => {
+                self.hold = Some(current_type);
+                let next_type = self.next_queue.pop_front().unwrap_or_else(|| next_from_bag(&mut self.bag));
+                self.next_queue.push_back(next_from_bag(&mut self.bag));
+                self.current_piece = Piece::new(next_type);
+            }
+        }
+        self.lock_deadline = None;
+        self.lock_resets = 0;
+        self.last_fall_time = get_time();
+    }
+
+    // Spawn a new piece from the next-piece queue, checking for game over
     fn spawn_new_piece(&mut self) {
-        self.current_piece = Piece::new(rand::gen_range(0, 7));
+        let next_type = self.next_queue.pop_front().unwrap_or_else(|| next_from_bag(&mut self.bag));
+        self.next_queue.push_back(next_from_bag(&mut self.bag));
+        self.current_piece = Piece::new(next_type);
+        self.hold_used = false;
         if !self.is_valid_position(&self.current_piece) {
             self.game_over = true;
         }
     }
-    
-    // Clear completed lines and update score
-    fn clear_lines(&mut self) {
-        let mut lines_cleared = 0;
-        let mut y = BOARD_HEIGHT - 1;
-
-        while y > 0 {
-            let is_full = self.board[y].iter().all(|&cell| cell != 0);
-            if is_full {
-                lines_cleared += 1;
-                // Move all lines above this one down
-                for row in (1..=y).rev() {
-                    self.board[row] = self.board[row - 1];
+}
+
+// Check whether `shape` placed at (x, y) on `board` is in bounds and free of
+// collisions. Shared between `GameState::is_valid_position` and the AI's
+// board simulations, which need to check placements against cloned boards.
+fn shape_fits(board: &Board, shape: &[[u8; 4]; 4], x: isize, y: isize) -> bool {
+    for sy in 0..4 {
+        for sx in 0..4 {
+            if shape[sy][sx] != 0 {
+                let board_x = x + sx as isize;
+                let board_y = y + sy as isize;
+
+                // Check bounds
+                if board_x < 0 || board_x >= BOARD_WIDTH as isize || board_y >= BOARD_HEIGHT as isize {
+                    return false;
+                }
+
+                // Check collision with existing blocks (only if on board)
+                if board_y >= 0 && board[board_y as usize][board_x as usize] != 0 {
+                    return false;
                 }
-                // Clear the top line
-                self.board[0] = [0; BOARD_WIDTH];
-            } else {
-                y -= 1;
             }
         }
-        
-        // Update score
-        self.score += match lines_cleared {
-            1 => 100,
-            2 => 300,
-            3 => 500,
-            4 => 800,
-            _ => 0,
-        };
     }
+    true
 }
 
 // --- Rotation Logic ---
@@ -241,26 +393,74 @@ fn handle_rotation(game: &mut GameState, direction: isize) {
         if game.is_valid_position(&final_piece) {
             game.current_piece = final_piece;
             game.current_piece.rotation = new_rotation;
+            game.reset_lock_delay();
             return; // Success!
         }
     }
 }
 
 
+// Draw a small preview of a tetromino (used for the next-piece queue and hold slot).
+fn draw_mini_shape(shape_type: usize, origin_x: f32, origin_y: f32, cell_size: f32) {
+    let shape = BASE_SHAPES[shape_type];
+    let color = TETROMINO_COLORS[shape_type + 1];
+    for y in 0..4 {
+        for x in 0..4 {
+            if shape[y][x] != 0 {
+                draw_rectangle(
+                    origin_x + x as f32 * cell_size,
+                    origin_y + y as f32 * cell_size,
+                    cell_size - 1.0,
+                    cell_size - 1.0,
+                    color,
+                );
+            }
+        }
+    }
+}
+
 // --- Main Game Loop ---
 
 #[macroquad::main("Tetris")]
 async fn main() {
     let mut game = GameState::new();
+    let mut ai_enabled = false;
+
+    // Connecting is best-effort: if no pad controller is plugged in, these
+    // are just `None` and the game plays keyboard-only as before.
+    #[cfg(feature = "midi")]
+    let midi_input = midi::MidiInputSource::connect().ok();
+    #[cfg(feature = "midi")]
+    let mut midi_output = midi::MidiOutputSink::connect().ok();
 
     loop {
+        // --- Handle MIDI Pad Input ---
+        #[cfg(feature = "midi")]
+        if let Some(input) = &midi_input {
+            for event in input.poll_events() {
+                midi::apply_control_event(&mut game, event);
+            }
+        }
+
+        if is_key_pressed(KeyCode::A) {
+            ai_enabled = !ai_enabled;
+        }
+
         // --- Handle Input ---
-        if !game.game_over {
+        if game.is_flashing() {
+            // Full rows are flashing; gravity and input pause until the flash
+            // window elapses and the rows actually collapse.
+        } else if !game.game_over && ai_enabled {
+            if let Some(target) = ai::best_placement(&game.board, game.current_piece.shape_type) {
+                ai::advance_toward(&mut game, &target);
+            }
+        } else if !game.game_over {
             if is_key_pressed(KeyCode::Left) {
                 let mut test_piece = game.current_piece;
                 test_piece.x -= 1;
                 if game.is_valid_position(&test_piece) {
                     game.current_piece.x -= 1;
+                    game.reset_lock_delay();
                 }
             }
             if is_key_pressed(KeyCode::Right) {
@@ -268,13 +468,16 @@ async fn main() {
                 test_piece.x += 1;
                 if game.is_valid_position(&test_piece) {
                     game.current_piece.x += 1;
+                    game.reset_lock_delay();
                 }
             }
-            if is_key_pressed(KeyCode::Down) {
+            if is_key_down(KeyCode::Down) {
                  let mut test_piece = game.current_piece;
                 test_piece.y += 1;
                 if game.is_valid_position(&test_piece) {
                     game.current_piece.y += 1;
+                    game.score += 1;
+                    game.reset_lock_delay();
                 }
             }
             if is_key_pressed(KeyCode::Up) {
@@ -283,11 +486,16 @@ async fn main() {
             if is_key_pressed(KeyCode::Z) {
                 handle_rotation(&mut game, -1); // Counter-clockwise
             }
+            if is_key_pressed(KeyCode::C) {
+                game.toggle_hold();
+            }
             if is_key_pressed(KeyCode::Space) {
+                let start_y = game.current_piece.y;
                 while game.is_valid_position(&game.current_piece) {
                     game.current_piece.y += 1;
                 }
                 game.current_piece.y -= 1; // Go back to last valid position
+                game.score += 2 * (game.current_piece.y - start_y) as u32;
                 game.lock_piece();
             }
         } else {
@@ -297,24 +505,51 @@ async fn main() {
         }
 
         // --- Update Game State (Gravity) ---
-        if !game.game_over && get_time() - game.last_fall_time > FALL_DELAY {
+        if !game.is_flashing() && !game.game_over && get_time() - game.last_fall_time > fall_delay_for_level(game.level) {
             let mut test_piece = game.current_piece;
             test_piece.y += 1;
             if game.is_valid_position(&test_piece) {
                 game.current_piece.y += 1;
-            } else {
-                game.lock_piece();
+                game.lock_deadline = None;
+                game.lock_resets = 0;
+            } else if game.lock_deadline.is_none() {
+                game.lock_deadline = Some(get_time() + LOCK_DELAY);
             }
             game.last_fall_time = get_time();
         }
 
+        // A grounded piece past its lock deadline hard-locks even if the
+        // player keeps nudging it (once MAX_LOCK_RESETS is exhausted above).
+        if !game.is_flashing() && !game.game_over {
+            if let Some(deadline) = game.lock_deadline {
+                if game.is_grounded() && get_time() >= deadline {
+                    game.lock_piece();
+                    game.lock_deadline = None;
+                    game.lock_resets = 0;
+                } else if !game.is_grounded() {
+                    game.lock_deadline = None;
+                    game.lock_resets = 0;
+                }
+            }
+        }
+
+        // Once the flash window elapses, actually collapse the rows and award score.
+        if game.is_flashing() && get_time() >= game.flash_deadline.unwrap() {
+            game.finish_line_clear();
+        }
+
         // --- Draw Everything ---
         clear_background(COLOR_BG);
 
-        // Draw locked pieces on the board
+        // Draw locked pieces on the board. Rows flagged for a line clear
+        // flash alternately white/normal for a brief window before collapsing.
+        let flash_is_white = (get_time() / 0.1) as i64 % 2 == 0;
         for (y, row) in game.board.iter().enumerate() {
             for (x, &cell) in row.iter().enumerate() {
-                let color = if cell == 0 { COLOR_GRID } else { TETROMINO_COLORS[cell as usize] };
+                let mut color = if cell == 0 { COLOR_GRID } else { TETROMINO_COLORS[cell as usize] };
+                if flash_is_white && game.flash_rows.contains(&y) {
+                    color = COLOR_UI_TEXT;
+                }
                 draw_rectangle(
                     BOARD_X_OFFSET + x as f32 * CELL_SIZE,
                     BOARD_Y_OFFSET + y as f32 * CELL_SIZE,
@@ -325,6 +560,28 @@ async fn main() {
             }
         }
 
+        // Draw the ghost piece: where the current piece would land if hard-dropped now
+        let mut ghost_piece = game.current_piece;
+        while game.is_valid_position(&ghost_piece) {
+            ghost_piece.y += 1;
+        }
+        ghost_piece.y -= 1;
+        let mut ghost_color = TETROMINO_COLORS[ghost_piece.shape_type + 1];
+        ghost_color.a = 0.3;
+        for y in 0..4 {
+            for x in 0..4 {
+                if ghost_piece.shape[y][x] != 0 {
+                    draw_rectangle(
+                        BOARD_X_OFFSET + (ghost_piece.x + x as isize) as f32 * CELL_SIZE,
+                        BOARD_Y_OFFSET + (ghost_piece.y + y as isize) as f32 * CELL_SIZE,
+                        CELL_SIZE - 1.0,
+                        CELL_SIZE - 1.0,
+                        ghost_color,
+                    );
+                }
+            }
+        }
+
         // Draw the current falling piece
         let piece_color = TETROMINO_COLORS[game.current_piece.shape_type + 1];
         for y in 0..4 {
@@ -343,6 +600,23 @@ async fn main() {
         
         // Draw UI
         draw_text(&format!("Score: {}", game.score), 20.0, 40.0, 40.0, COLOR_UI_TEXT);
+        draw_text(&format!("Level: {}", game.level), 20.0, 80.0, 40.0, COLOR_UI_TEXT);
+        if ai_enabled {
+            draw_text("AI: ON (A to toggle)", 20.0, 110.0, 24.0, COLOR_UI_TEXT);
+        }
+
+        // Hold slot, in the left-hand margin
+        draw_text("Hold", 20.0, 140.0, 28.0, COLOR_UI_TEXT);
+        if let Some(shape_type) = game.hold {
+            draw_mini_shape(shape_type, 20.0, 160.0, 18.0);
+        }
+
+        // Next-piece preview, in the right-hand margin
+        let next_preview_x = BOARD_X_OFFSET + BOARD_WIDTH as f32 * CELL_SIZE + 10.0;
+        draw_text("Next", next_preview_x, 40.0, 28.0, COLOR_UI_TEXT);
+        for (i, &shape_type) in game.next_queue.iter().enumerate() {
+            draw_mini_shape(shape_type, next_preview_x, 60.0 + i as f32 * 80.0, 18.0);
+        }
 
         if game.game_over {
             let text = "GAME OVER";
@@ -354,6 +628,12 @@ async fn main() {
             draw_text(restart_text, SCREEN_WIDTH / 2.0 - restart_text_dims.width / 2.0, SCREEN_HEIGHT / 2.0 + 50.0, 30.0, COLOR_UI_TEXT);
         }
 
+        // --- Mirror the board onto the MIDI pad grid's LEDs ---
+        #[cfg(feature = "midi")]
+        if let Some(output) = &mut midi_output {
+            output.render(&game);
+        }
+
         next_frame().await
     }
 }
@@ -0,0 +1,219 @@
+//! Optional MIDI grid-controller backend.
+//!
+//! Mirrors the note<->(x, y) pad mapping and `ControlEvent` pattern used by
+//! Launchpad-style clones: incoming note-on messages from a MIDI pad grid are
+//! turned into `ControlEvent`s that drive `GameState` the same way keyboard
+//! input does in `main`, and the board can be mirrored back out to the
+//! controller's LEDs by sending note-on messages with per-cell velocities.
+//!
+//! This module is gated behind the `midi` feature since it pulls in `midir`
+//! and most players won't have a pad controller plugged in.
+
+use crate::{GameState, BOARD_WIDTH, BOARD_HEIGHT, TETROMINO_COLORS};
+use midir::{Ignore, MidiInput, MidiInputConnection, MidiOutput, MidiOutputConnection};
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+/// Real pad grids (Launchpad-style) are commonly 8x8, narrower than the
+/// 10-wide board, so output mirrors a fixed column window rather than the
+/// whole board.
+const PAD_GRID_WIDTH: usize = 8;
+const PAD_GRID_HEIGHT: usize = 8;
+const PAD_COLUMN_OFFSET: usize = 1;
+
+/// Actions a pad controller can trigger, mirrored 1:1 onto the inputs the
+/// keyboard already drives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlEvent {
+    MoveLeft,
+    MoveRight,
+    MoveDown,
+    Rotate,
+    Drop,
+    Hold,
+    Restart,
+}
+
+/// Fixed control pads, notes 0-6: row 0 of the grid in `light_pad`'s
+/// mapping, the same pads that mirror the board's top row.
+fn note_to_event(note: u8) -> Option<ControlEvent> {
+    match note {
+        0 => Some(ControlEvent::MoveLeft),
+        1 => Some(ControlEvent::MoveRight),
+        2 => Some(ControlEvent::MoveDown),
+        3 => Some(ControlEvent::Rotate),
+        4 => Some(ControlEvent::Drop),
+        5 => Some(ControlEvent::Hold),
+        6 => Some(ControlEvent::Restart),
+        _ => None,
+    }
+}
+
+/// Apply a `ControlEvent` to `game`, the same way the keyboard handlers in
+/// `main` apply key presses.
+pub fn apply_control_event(game: &mut GameState, event: ControlEvent) {
+    // Like the keyboard: nothing while rows flash, and only Restart once the
+    // game is over.
+    if game.is_flashing() || (game.game_over && event != ControlEvent::Restart) {
+        return;
+    }
+    match event {
+        ControlEvent::MoveLeft => {
+            let mut test_piece = game.current_piece;
+            test_piece.x -= 1;
+            if game.is_valid_position(&test_piece) {
+                game.current_piece.x -= 1;
+                game.reset_lock_delay();
+            }
+        }
+        ControlEvent::MoveRight => {
+            let mut test_piece = game.current_piece;
+            test_piece.x += 1;
+            if game.is_valid_position(&test_piece) {
+                game.current_piece.x += 1;
+                game.reset_lock_delay();
+            }
+        }
+        ControlEvent::MoveDown => {
+            let mut test_piece = game.current_piece;
+            test_piece.y += 1;
+            if game.is_valid_position(&test_piece) {
+                game.current_piece.y += 1;
+                game.score += 1;
+                game.reset_lock_delay();
+            }
+        }
+        ControlEvent::Rotate => crate::handle_rotation(game, 1),
+        ControlEvent::Drop => {
+            let start_y = game.current_piece.y;
+            while game.is_valid_position(&game.current_piece) {
+                game.current_piece.y += 1;
+            }
+            game.current_piece.y -= 1;
+            game.score += 2 * (game.current_piece.y - start_y) as u32;
+            game.lock_piece();
+        }
+        ControlEvent::Hold => game.toggle_hold(),
+        ControlEvent::Restart => {
+            if game.game_over {
+                game.reset();
+            }
+        }
+    }
+}
+
+/// Input side: listens for note-on messages on a MIDI input port and turns
+/// them into `ControlEvent`s on an mpsc channel.
+pub struct MidiInputSource {
+    _connection: MidiInputConnection<()>,
+    receiver: Receiver<ControlEvent>,
+}
+
+impl MidiInputSource {
+    /// Connect to the first available MIDI input port.
+    pub fn connect() -> Result<Self, String> {
+        let mut midi_in = MidiInput::new("tetris-pad-input").map_err(|e| e.to_string())?;
+        midi_in.ignore(Ignore::None);
+
+        let ports = midi_in.ports();
+        let port = ports.first().ok_or("no MIDI input ports available")?;
+
+        let (sender, receiver): (Sender<ControlEvent>, Receiver<ControlEvent>) = channel();
+        let connection = midi_in
+            .connect(
+                port,
+                "tetris-pad-input",
+                move |_timestamp, message, sender: &mut Sender<ControlEvent>| {
+                    if let [status, note, velocity] = *message {
+                        let is_note_on = status & 0xF0 == 0x90 && velocity > 0;
+                        if is_note_on {
+                            if let Some(event) = note_to_event(note) {
+                                let _ = sender.send(event);
+                            }
+                        }
+                    }
+                },
+                sender,
+            )
+            .map_err(|e| e.to_string())?;
+
+        Ok(MidiInputSource {
+            _connection: connection,
+            receiver,
+        })
+    }
+
+    /// Drain every event received since the last poll.
+    pub fn poll_events(&self) -> Vec<ControlEvent> {
+        self.receiver.try_iter().collect()
+    }
+}
+
+/// Output side: lights an 8-wide pad grid to mirror the top of `board` plus
+/// the falling piece.
+pub struct MidiOutputSink {
+    connection: MidiOutputConnection,
+}
+
+impl MidiOutputSink {
+    /// Connect to the first available MIDI output port.
+    pub fn connect() -> Result<Self, String> {
+        let midi_out = MidiOutput::new("tetris-pad-output").map_err(|e| e.to_string())?;
+        let ports = midi_out.ports();
+        let port = ports.first().ok_or("no MIDI output ports available")?;
+        let connection = midi_out.connect(port, "tetris-pad-output").map_err(|e| e.to_string())?;
+        Ok(MidiOutputSink { connection })
+    }
+
+    /// Mirror the top `PAD_GRID_HEIGHT` rows of the board plus the falling
+    /// piece onto the pad grid's LEDs, one note-on per cell with a velocity
+    /// derived from `TETROMINO_COLORS`.
+    pub fn render(&mut self, game: &GameState) {
+        for row in 0..PAD_GRID_HEIGHT.min(BOARD_HEIGHT) {
+            for col in 0..PAD_GRID_WIDTH {
+                let board_col = col + PAD_COLUMN_OFFSET;
+                if board_col >= BOARD_WIDTH {
+                    continue;
+                }
+                let cell = game.board[row][board_col];
+                let velocity = cell_velocity(cell);
+                self.light_pad(col, row, velocity);
+            }
+        }
+
+        let piece = &game.current_piece;
+        for y in 0..4 {
+            for x in 0..4 {
+                if piece.shape[y][x] == 0 {
+                    continue;
+                }
+                let board_col = (piece.x + x as isize) as usize;
+                let board_row = (piece.y + y as isize) as usize;
+                if board_row >= PAD_GRID_HEIGHT || board_col < PAD_COLUMN_OFFSET {
+                    continue;
+                }
+                let col = board_col - PAD_COLUMN_OFFSET;
+                if col >= PAD_GRID_WIDTH {
+                    continue;
+                }
+                let velocity = cell_velocity(piece.shape_type as u8 + 1);
+                self.light_pad(col, board_row, velocity);
+            }
+        }
+    }
+
+    fn light_pad(&mut self, col: usize, row: usize, velocity: u8) {
+        let note = (row * PAD_GRID_WIDTH + col) as u8;
+        let _ = self.connection.send(&[0x90, note, velocity]);
+    }
+}
+
+/// Map a board cell (0 = empty, 1-7 = tetromino type + 1) to a MIDI velocity
+/// using the brightness of its `TETROMINO_COLORS` entry.
+fn cell_velocity(cell: u8) -> u8 {
+    if cell == 0 {
+        return 0;
+    }
+    let color = TETROMINO_COLORS[cell as usize];
+    let brightness = (color.r + color.g + color.b) / 3.0;
+    (brightness * 127.0) as u8
+}